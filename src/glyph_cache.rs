@@ -0,0 +1,333 @@
+//! Bounded, evictable glyph raster cache.
+//!
+//! Rasterizing a character onto a `GrayImage` is the expensive part of
+//! `pxmatch` mode, so results are cached in two layers: an in-memory LRU
+//! (`GlyphCache`) bounded by a byte budget, backed by an on-disk directory
+//! that is garbage-collected oldest-file-first once it grows past its own cap.
+//! `MemoryReport` exposes enough bookkeeping for the TUI to show cache
+//! health in its status line.
+
+use image::{GrayImage, ImageBuffer, Luma};
+use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies a rasterized glyph: the grapheme cluster plus the tile size
+/// it was rendered at, since the same glyph at a different size is a
+/// different bitmap. A `String` (not `char`) so multi-codepoint grapheme
+/// clusters from a user-supplied palette can be cached too.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub glyph: String,
+    pub tile_w: u32,
+    pub tile_h: u32,
+}
+
+/// Cache health snapshot, suitable for display in the TUI status line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoryReport {
+    pub glyph_count: usize,
+    pub resident_bytes: u64,
+    pub disk_bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct Entry {
+    img: ImageBuffer<Luma<u8>, Vec<u8>>,
+    bytes: u64,
+    last_used: u64,
+}
+
+struct DiskEntry {
+    path: PathBuf,
+    bytes: u64,
+    written_at: u64,
+}
+
+/// In-memory LRU cache of rasterized glyphs, backed by an on-disk L2 cache.
+/// Both layers are bounded by a byte budget and evict entries once an insert
+/// pushes them past it: the memory layer drops least-recently-used entries,
+/// the disk layer drops the oldest-written files (a disk hit does not count
+/// as a use). The disk layer is keyed by the
+/// hashed filename (not `GlyphKey`) so files written by an earlier process
+/// can be rediscovered and GC'd without knowing the glyph that produced them.
+pub struct GlyphCache {
+    disk_dir: PathBuf,
+    memory_budget_bytes: u64,
+    disk_budget_bytes: u64,
+    resident_bytes: u64,
+    disk_bytes: u64,
+    entries: HashMap<GlyphKey, Entry>,
+    disk_files: HashMap<String, DiskEntry>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl GlyphCache {
+    pub fn new(disk_dir: impl Into<PathBuf>, memory_budget_mb: u64, disk_budget_mb: u64) -> Self {
+        let disk_dir = disk_dir.into();
+        if !disk_dir.exists() {
+            fs::create_dir_all(&disk_dir).unwrap();
+        }
+        let mut cache = GlyphCache {
+            disk_dir,
+            memory_budget_bytes: memory_budget_mb * 1024 * 1024,
+            disk_budget_bytes: disk_budget_mb * 1024 * 1024,
+            resident_bytes: 0,
+            disk_bytes: 0,
+            entries: HashMap::new(),
+            disk_files: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        };
+        cache.scan_disk_dir();
+        cache
+    }
+
+    /// Registers files already sitting in `disk_dir` from a prior run, so
+    /// the disk budget/GC applies across invocations rather than only to
+    /// files written by this process.
+    fn scan_disk_dir(&mut self) {
+        let Ok(read_dir) = fs::read_dir(&self.disk_dir) else {
+            return;
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let Some(filename) = path.file_name().and_then(|f| f.to_str()) else {
+                continue;
+            };
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let bytes = metadata.len();
+            let written_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            self.disk_bytes += bytes;
+            self.disk_files.insert(
+                filename.to_string(),
+                DiskEntry {
+                    path,
+                    bytes,
+                    written_at,
+                },
+            );
+        }
+        self.evict_disk_if_needed();
+    }
+
+    /// Returns the rasterized glyph for `key`, calling `rasterize` to
+    /// produce it on a cache miss (checking the disk layer first).
+    pub fn get_or_insert_with<F>(&mut self, key: GlyphKey, rasterize: F) -> GrayImage
+    where
+        F: FnOnce() -> GrayImage,
+    {
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.clock;
+            self.hits += 1;
+            return entry.img.clone();
+        }
+
+        let filename = Self::disk_filename(&key);
+        let img = match self.disk_files.get(&filename) {
+            Some(disk_entry) => {
+                self.hits += 1;
+                image::open(&disk_entry.path).unwrap().to_luma8()
+            }
+            None => {
+                self.misses += 1;
+                let img = rasterize();
+                self.write_to_disk(filename, &img);
+                img
+            }
+        };
+
+        self.insert_resident(key, img.clone());
+        img
+    }
+
+    fn disk_filename(key: &GlyphKey) -> String {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        format!("{}.png", hasher.finish())
+    }
+
+    fn write_to_disk(&mut self, filename: String, img: &GrayImage) {
+        let path = self.disk_dir.join(&filename);
+        img.save(&path).unwrap();
+        let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let written_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.disk_bytes += bytes;
+        self.disk_files.insert(
+            filename,
+            DiskEntry {
+                path,
+                bytes,
+                written_at,
+            },
+        );
+        self.evict_disk_if_needed();
+    }
+
+    fn insert_resident(&mut self, key: GlyphKey, img: GrayImage) {
+        let bytes = (img.width() * img.height()) as u64;
+        self.resident_bytes += bytes;
+        self.entries.insert(
+            key,
+            Entry {
+                img,
+                bytes,
+                last_used: self.clock,
+            },
+        );
+        self.evict_memory_if_needed();
+    }
+
+    fn evict_memory_if_needed(&mut self) {
+        while self.resident_bytes > self.memory_budget_bytes {
+            let Some(lru_key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&lru_key) {
+                self.resident_bytes -= entry.bytes;
+            }
+        }
+    }
+
+    fn evict_disk_if_needed(&mut self) {
+        while self.disk_bytes > self.disk_budget_bytes {
+            let Some(oldest_key) = self
+                .disk_files
+                .iter()
+                .min_by_key(|(_, entry)| entry.written_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.disk_files.remove(&oldest_key) {
+                let _ = fs::remove_file(entry.path);
+                self.disk_bytes -= entry.bytes;
+            }
+        }
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        MemoryReport {
+            glyph_count: self.entries.len(),
+            resident_bytes: self.resident_bytes,
+            disk_bytes: self.disk_bytes,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "glyph_cache_test_{}_{}_{}",
+            name,
+            std::process::id(),
+            nonce
+        ))
+    }
+
+    fn sample_img() -> GrayImage {
+        GrayImage::from_pixel(4, 4, Luma([128]))
+    }
+
+    #[test]
+    fn disk_cache_is_rescanned_across_instances() {
+        let dir = temp_dir("persist");
+        {
+            let mut cache = GlyphCache::new(&dir, 1, 1);
+            cache.get_or_insert_with(
+                GlyphKey {
+                    glyph: "A".to_string(),
+                    tile_w: 4,
+                    tile_h: 4,
+                },
+                sample_img,
+            );
+            assert!(cache.report().disk_bytes > 0);
+        }
+
+        // a fresh instance pointed at the same directory should see the
+        // previous run's files without writing anything new
+        let cache = GlyphCache::new(&dir, 1, 1);
+        assert!(
+            cache.report().disk_bytes > 0,
+            "disk usage from a prior run should be visible to a fresh instance"
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn disk_hits_are_counted() {
+        let dir = temp_dir("hits");
+        let key = GlyphKey {
+            glyph: "B".to_string(),
+            tile_w: 4,
+            tile_h: 4,
+        };
+        {
+            // a 0 MB memory budget evicts every entry from the resident
+            // layer immediately, so the second lookup below can only be
+            // served by the disk layer
+            let mut cache = GlyphCache::new(&dir, 0, 1);
+            cache.get_or_insert_with(key.clone(), sample_img);
+        }
+
+        let mut cache = GlyphCache::new(&dir, 0, 1);
+        cache.get_or_insert_with(key, sample_img);
+        assert_eq!(cache.report().hits, 1);
+        assert_eq!(cache.report().misses, 0);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn stale_disk_files_are_evicted_on_scan() {
+        let dir = temp_dir("evict");
+        fs::create_dir_all(&dir).unwrap();
+        let stale = dir.join("stale.png");
+        fs::write(&stale, vec![0u8; 1000]).unwrap();
+
+        // a 0 MB disk budget means the scan should immediately GC anything
+        // left over from a previous run, not just files written this process
+        let cache = GlyphCache::new(&dir, 1, 0);
+        assert_eq!(cache.report().disk_bytes, 0);
+        assert!(!stale.exists());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
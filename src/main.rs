@@ -1,6 +1,7 @@
 use clap::Parser;
 use crossterm::event::{Event, KeyEvent};
-use image::{GenericImageView, GrayImage, ImageBuffer, Luma};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, GenericImageView, GrayImage, ImageBuffer, Luma};
 use imageproc::{drawing::draw_text_mut, template_matching::match_template};
 use ratatui::{
     crossterm::event::{self, KeyCode, KeyEventKind},
@@ -9,21 +10,28 @@ use ratatui::{
     widgets::{block::Title, Block, Paragraph},
     DefaultTerminal, Frame,
 };
+use rayon::prelude::*;
 use rusttype::{Font, Scale};
-use std::collections::HashMap;
 use std::fs;
 use std::io;
-use std::time::Instant;
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-    path::Path,
-};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+mod glyph_cache;
+mod script;
+mod sixel;
+
+use glyph_cache::{GlyphCache, GlyphKey, MemoryReport};
+use script::Script;
 
 #[derive(Debug, Clone)]
 enum Mode {
     Values,
     PixelMatch,
+    Sixel,
 }
 
 impl std::fmt::Display for Mode {
@@ -31,6 +39,7 @@ impl std::fmt::Display for Mode {
         let s = match self {
             Mode::Values => "values",
             Mode::PixelMatch => "pxmatch",
+            Mode::Sixel => "sixel",
         };
         s.fmt(f)
     }
@@ -42,6 +51,7 @@ impl std::str::FromStr for Mode {
         match s {
             "values" => Ok(Mode::Values),
             "pxmatch" => Ok(Mode::PixelMatch),
+            "sixel" => Ok(Mode::Sixel),
             _ => Err(format!("Unknown mode: {}", s)),
         }
     }
@@ -55,12 +65,53 @@ const PALETTE : [&str; 4] = [
     " !@#$%^&*()-=_+`~qwfpgjluy;[]arstdhneio'zxcvbkm,./\\|QWFPGJLUY:{}ARSTDHNEIO\"ZXCVBKM<>?",
 ];
 
-fn validate_palette_index(s: &str) -> Result<usize, String> {
-    let index = s.parse::<usize>().map_err(|e| e.to_string())?;
-    if index >= PALETTE.len() {
-        Err(format!("Invalid palette index: {}", index))
+/// Resolves a `--palette` spec to the chars it names: a numeric index into
+/// the built-in `PALETTE` array, or (when a script is loaded) the name of a
+/// palette the script registered via `register_palette`. Rejects an empty
+/// palette, since it has no glyph to map any pixel value onto.
+fn resolve_palette(spec: &str, script: Option<&Script>) -> Result<String, String> {
+    let palette = if let Ok(index) = spec.parse::<usize>() {
+        PALETTE
+            .get(index)
+            .map(|p| p.to_string())
+            .ok_or_else(|| format!("Invalid palette index: {}", index))?
+    } else {
+        script
+            .and_then(|script| script.palette(spec))
+            .ok_or_else(|| format!("Unknown palette: {}", spec))?
+    };
+    if palette.is_empty() {
+        return Err(format!("Palette {:?} is empty", spec));
+    }
+    Ok(palette)
+}
+
+/// Clap `value_parser` for `--sixel-colors`: rejects 0, which would
+/// otherwise quantize to an empty palette and emit a blank SIXEL payload
+/// with no color registers and no pixel runs.
+fn parse_sixel_colors(s: &str) -> Result<usize, String> {
+    let v: usize = s
+        .parse()
+        .map_err(|_| format!("Invalid color count: {}", s))?;
+    if v == 0 {
+        Err("Sixel color count must be at least 1".to_string())
+    } else {
+        Ok(v)
+    }
+}
+
+/// Clap `value_parser` for `--cell-aspect`: rejects non-positive ratios,
+/// which would otherwise divide `rows` by zero or a negative number and
+/// either abort the process with an OOM allocation or silently render a
+/// degenerate 0-row image.
+fn parse_cell_aspect(s: &str) -> Result<f32, String> {
+    let v: f32 = s
+        .parse()
+        .map_err(|_| format!("Invalid cell aspect ratio: {}", s))?;
+    if v > 0.0 {
+        Ok(v)
     } else {
-        Ok(index)
+        Err(format!("Cell aspect ratio must be greater than 0, got {}", v))
     }
 }
 
@@ -80,13 +131,50 @@ struct Args {
     #[arg(short = 'I', long)]
     invert: bool,
 
-    /// Mode (values, pxmatch)
+    /// Mode (values, pxmatch, sixel)
     #[arg(short, long, default_value_t = Mode::Values)]
     mode: Mode,
 
-    /// Palette
-    #[arg(short, long, default_value = "0", value_parser = validate_palette_index)]
-    palette: usize,
+    /// Palette: either a built-in index, or (with --script) a registered palette name
+    #[arg(short, long, default_value = "0")]
+    palette: String,
+
+    /// Rhai script defining custom `map`/`preprocess` hooks and named palettes
+    #[arg(long)]
+    script: Option<String>,
+
+    /// Number of quantized colors used by the SIXEL encoder (mode = sixel)
+    #[arg(long, default_value = "16", value_parser = parse_sixel_colors)]
+    sixel_colors: usize,
+
+    /// Override the animation/video playback rate (frames per second)
+    /// instead of using each GIF frame's own delay
+    #[arg(long)]
+    fps: Option<f32>,
+
+    /// Number of threads used for pxmatch template matching (default: all cores)
+    #[arg(long)]
+    threads: Option<usize>,
+
+    /// In-memory glyph cache budget, in megabytes
+    #[arg(long, default_value = "16")]
+    glyph_cache_mb: u64,
+
+    /// On-disk glyph cache budget, in megabytes
+    #[arg(long, default_value = "64")]
+    disk_cache_mb: u64,
+
+    /// Terminal cell height-to-width ratio, used to keep the sampled image
+    /// from looking stretched (most monospace fonts render around 2:1)
+    #[arg(long, default_value = "2.0", value_parser = parse_cell_aspect)]
+    cell_aspect: f32,
+}
+
+/// Splits a palette into grapheme clusters rather than `char`s, so a
+/// custom palette mixing single codepoints with multi-codepoint glyphs
+/// (flag emoji, combining marks) indexes correctly.
+fn palette_glyphs(palette: &str) -> Vec<&str> {
+    palette.graphemes(true).collect()
 }
 
 fn paint_values(
@@ -95,31 +183,36 @@ fn paint_values(
     line_height: f32,
     invert: bool,
     palette: &str,
-) -> Vec<char> {
+    script: Option<&Script>,
+) -> Vec<String> {
+    let glyphs = palette_glyphs(palette);
     let ar = img.width() as f32 / img.height() as f32;
     let rows = (cols as f32 / (ar * line_height)) as u32;
     let char_matrix = img
         .resize_exact(cols, rows, image::imageops::FilterType::Nearest)
         .to_luma8()
-        .pixels()
-        .map(|p| {
+        .enumerate_pixels()
+        .map(|(x, y, p)| {
             let mut v = p.0[0];
             v = if invert { 255 - v } else { v };
-            palette
-                .chars()
-                .nth((palette.len() as f32 * (v as f32 / 256.0)) as usize)
-                .unwrap()
+            if let Some(script) = script {
+                v = script.preprocess(v, x, y);
+                if script.has_map() {
+                    return script.map(v, x, y);
+                }
+            }
+            glyphs[(glyphs.len() as f32 * (v as f32 / 256.0)) as usize].to_string()
         })
-        .collect::<Vec<char>>();
+        .collect::<Vec<String>>();
 
     char_matrix
 }
 
 fn generate_char_imgs(
-    chars: &Vec<char>,
+    glyphs: &[String],
     tile_w: u32,
     tile_h: u32,
-    char_img_cache: &mut HashMap<char, ImageBuffer<Luma<u8>, Vec<u8>>>,
+    glyph_cache: &mut GlyphCache,
 ) -> Vec<ImageBuffer<Luma<u8>, Vec<u8>>> {
     let start = Instant::now();
 
@@ -132,35 +225,20 @@ fn generate_char_imgs(
     );
     let font = Font::try_from_vec(font).unwrap();
 
-    let char_imgs = chars
+    let char_imgs = glyphs
         .iter()
-        .enumerate()
-        .map(|(_, c)| {
-            // check memory cache
-            if let Some(img) = char_img_cache.get(c) {
-                return img.clone();
-            }
-
-            // check disk cache
-            let mut hasher = DefaultHasher::new();
-            c.hash(&mut hasher);
-            tile_w.hash(&mut hasher);
-            tile_h.hash(&mut hasher);
-            let hash = hasher.finish();
-            let cache_file = format!("cache/{}.png", hash);
-
-            if Path::new(&cache_file).exists() {
-                return image::open(cache_file).unwrap().to_luma8();
-            }
-
-            let mut char_img = GrayImage::new(tile_w, tile_h);
-            char_img.fill(255);
-            draw_text_mut(&mut char_img, Luma([0]), 0, 0, scale, &font, &c.to_string());
-
-            char_img.save(&cache_file).unwrap();
-            char_img_cache.insert(*c, char_img.clone());
-
-            char_img
+        .map(|glyph| {
+            let key = GlyphKey {
+                glyph: glyph.clone(),
+                tile_w,
+                tile_h,
+            };
+            glyph_cache.get_or_insert_with(key, || {
+                let mut char_img = GrayImage::new(tile_w, tile_h);
+                char_img.fill(255);
+                draw_text_mut(&mut char_img, Luma([0]), 0, 0, scale, &font, glyph);
+                char_img
+            })
         })
         .collect::<Vec<GrayImage>>();
 
@@ -176,8 +254,9 @@ fn paint_flat(
     line_height: f32,
     invert: bool,
     palette: &str,
-    char_img_cache: &mut HashMap<char, ImageBuffer<Luma<u8>, Vec<u8>>>,
-) -> Vec<char> {
+    glyph_cache: &mut GlyphCache,
+    threads: Option<usize>,
+) -> Vec<String> {
     let tile_w = 10;
     let tile_h = tile_w * line_height as u32;
     let w = cols * tile_w;
@@ -193,11 +272,16 @@ fn paint_flat(
         img.invert()
     }
 
-    let chars = palette.chars().collect::<Vec<char>>();
-    let mut char_matrix = vec!['*'; (cols * rows) as usize];
-    let char_imgs = generate_char_imgs(&chars, tile_w, tile_h, char_img_cache);
+    let glyphs = palette_glyphs(palette)
+        .into_iter()
+        .map(|g| g.to_string())
+        .collect::<Vec<String>>();
+    let char_imgs = generate_char_imgs(&glyphs, tile_w, tile_h, glyph_cache);
 
-    for i in 0..(cols * rows) {
+    // char_imgs is read-only past this point, so every tile's best-match
+    // search (cols*rows*palette.len() match_template calls) can run on its
+    // own thread; only the final char_matrix is gathered back.
+    let best_match_for_tile = |i: u32| -> String {
         let tile = img
             .crop_imm(
                 (i % cols) * tile_w,
@@ -208,8 +292,7 @@ fn paint_flat(
             .to_luma8();
         if tile.width() != tile_w || tile.height() != tile_h {
             println!("tile size mismatch {} {}", tile.width(), tile.height());
-            char_matrix[i as usize] = '_';
-            continue;
+            return "_".to_string();
         }
 
         // tests all chars agaist tile
@@ -218,7 +301,7 @@ fn paint_flat(
         let mut best_score = u32::MAX;
         for (ci, char_img) in char_imgs.iter().enumerate() {
             let matched = match_template(
-                &char_img,
+                char_img,
                 &tile,
                 // imageproc::template_matching::MatchTemplateMethod::CrossCorrelation,
                 imageproc::template_matching::MatchTemplateMethod::SumOfSquaredErrors,
@@ -230,32 +313,78 @@ fn paint_flat(
                 best_score = score;
             }
         }
-        char_matrix[i as usize] = chars[best];
-    }
+        glyphs[best].clone()
+    };
+
+    let compute = || {
+        (0..(cols * rows))
+            .into_par_iter()
+            .map(best_match_for_tile)
+            .collect()
+    };
+
+    let char_matrix: Vec<String> = match threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(compute),
+        None => compute(),
+    };
 
     char_matrix
 }
 
 #[derive(Debug, Default)]
 struct App {
-    result: String,
+    /// one rendered frame per animation frame (a single-image run has one)
+    frames: Vec<String>,
+    delays: Vec<Duration>,
+    current: usize,
+    playing: bool,
+    reverse: bool,
+    last_tick: Option<Instant>,
+    memory_report: MemoryReport,
     exit: bool,
 }
 
 impl App {
+    fn new(frames: Vec<String>, delays: Vec<Duration>, memory_report: MemoryReport) -> Self {
+        App {
+            frames,
+            delays,
+            current: 0,
+            playing: true,
+            reverse: false,
+            last_tick: None,
+            memory_report,
+            exit: false,
+        }
+    }
+
     fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        self.last_tick = Some(Instant::now());
         while !self.exit {
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_events()?;
+            self.advance();
         }
         Ok(())
     }
 
     fn draw(&self, frame: &mut Frame) {
+        let status = format!(
+            "Canvas — glyphs: {} ({} B resident / {} B disk, {} hits / {} misses)",
+            self.memory_report.glyph_count,
+            self.memory_report.resident_bytes,
+            self.memory_report.disk_bytes,
+            self.memory_report.hits,
+            self.memory_report.misses,
+        );
         let block = Block::bordered()
-            .title(Title::from("Canvas"))
+            .title(Title::from(status))
             .border_set(border::ROUNDED);
-        let greeting = Paragraph::new(self.result.clone())
+        let greeting = Paragraph::new(self.frames[self.current].clone())
             .white()
             .on_black()
             .block(block);
@@ -264,83 +393,458 @@ impl App {
 
     /// updates the application's state based on user input
     fn handle_events(&mut self) -> io::Result<()> {
-        match event::read()? {
-            // it's important to check that the event is a key press event as
-            // crossterm also emits key release and repeat events on Windows.
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
-            }
-            _ => {}
-        };
+        // a short poll (instead of a blocking read) lets playback advance
+        // between keystrokes
+        if event::poll(Duration::from_millis(16))? {
+            match event::read()? {
+                // it's important to check that the event is a key press event as
+                // crossterm also emits key release and repeat events on Windows.
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    self.handle_key_event(key_event)
+                }
+                _ => {}
+            };
+        }
         Ok(())
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
         match key_event.code {
             KeyCode::Char('q') => self.exit(),
+            KeyCode::Char(' ') => self.playing = !self.playing,
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            KeyCode::Right => {
+                self.playing = false;
+                self.step(true);
+            }
+            KeyCode::Left => {
+                self.playing = false;
+                self.step(false);
+            }
             _ => {}
         }
     }
 
+    /// advances playback by one frame once the current frame's delay has elapsed
+    fn advance(&mut self) {
+        if !self.playing || self.frames.len() <= 1 {
+            return;
+        }
+        let delay = self.delays[self.current];
+        let elapsed = self.last_tick.map(|t| t.elapsed()).unwrap_or_default();
+        if elapsed >= delay {
+            self.step(!self.reverse);
+            self.last_tick = Some(Instant::now());
+        }
+    }
+
+    fn step(&mut self, forward: bool) {
+        let len = self.frames.len();
+        if len == 0 {
+            return;
+        }
+        self.current = if forward {
+            (self.current + 1) % len
+        } else {
+            (self.current + len - 1) % len
+        };
+    }
+
     fn exit(&mut self) {
         self.exit = true;
     }
 }
 
-fn paint(args: Args, img: image::DynamicImage) -> String {
-    let line_height = 2.0;
-    // memory cache will be usefull to process batches of images or video stream
-    let mut char_img_cache: HashMap<char, ImageBuffer<Luma<u8>, Vec<u8>>> = HashMap::new();
+/// Renders a flat `char_matrix` (one glyph per sampled column, `cols` per
+/// row) into display lines. Glyphs are grapheme clusters, which may be
+/// double-width (CJK, some Nerd Font icons); a double-width glyph already
+/// fills the terminal-column budget of the cell after it, so that cell's
+/// sampled glyph is dropped to keep every line exactly `cols` columns wide.
+/// A double-width glyph landing in the row's last column would overshoot
+/// `cols` by one, so it's replaced with a single-column space filler instead.
+fn render_char_matrix(char_matrix: &[String], cols: u32) -> String {
+    let cols = cols as usize;
+    if cols == 0 {
+        return String::new();
+    }
+    let mut result = String::new();
+    for row in char_matrix.chunks(cols) {
+        let mut used = 0;
+        let mut j = 0;
+        while used < cols && j < row.len() {
+            let glyph = &row[j];
+            let width = glyph.width().max(1);
+            if used + width > cols {
+                result.push(' ');
+                used += 1;
+                j += 1;
+                continue;
+            }
+            result.push_str(glyph);
+            used += width;
+            j += width;
+        }
+        for _ in used..cols {
+            result.push(' ');
+        }
+        result.push('\n');
+    }
+    result
+}
+
+fn paint(
+    args: &Args,
+    img: &image::DynamicImage,
+    glyph_cache: &mut GlyphCache,
+    palette: &str,
+    script: Option<&Script>,
+) -> String {
+    let line_height = args.cell_aspect;
     let char_matrix = match args.mode {
-        Mode::Values => paint_values(
-            &img,
-            args.cols,
-            line_height,
-            args.invert,
-            PALETTE[args.palette],
-        ),
+        Mode::Values => paint_values(img, args.cols, line_height, args.invert, palette, script),
         Mode::PixelMatch => paint_flat(
-            &img,
+            img,
             args.cols,
             line_height,
             args.invert,
-            PALETTE[args.palette],
-            &mut char_img_cache,
+            palette,
+            glyph_cache,
+            args.threads,
         ),
+        Mode::Sixel => unreachable!("Mode::Sixel is handled before paint() is called"),
     };
 
-    let mut result = String::new();
-    for (i, c) in char_matrix.iter().enumerate() {
-        result.push(c.clone());
-        if (i + 1) % args.cols as usize == 0 {
-            result.push('\n');
-        }
-    }
-    result
+    render_char_matrix(&char_matrix, args.cols)
+}
+
+/// A single decoded animation frame together with how long it should be
+/// shown for (its own GIF delay, or the `--fps` override).
+struct AnimFrame {
+    img: image::DynamicImage,
+    delay: Duration,
+}
+
+fn is_gif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+}
+
+fn load_gif_frames(path: &Path, fps: Option<f32>) -> io::Result<Vec<AnimFrame>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    let decoder =
+        GifDecoder::new(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let frames = decoder
+        .into_frames()
+        .collect_frames()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let delay = match fps {
+                Some(fps) => Duration::from_secs_f32(1.0 / fps),
+                None => {
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    let ms = numer.checked_div(denom).unwrap_or(numer);
+                    Duration::from_millis(ms as u64)
+                }
+            };
+            AnimFrame {
+                img: image::DynamicImage::ImageRgba8(frame.into_buffer()),
+                delay,
+            }
+        })
+        .collect())
+}
+
+/// paints every decoded frame, reusing a single glyph cache across the whole
+/// animation so playback cost is resampling + matching, not re-rasterizing
+fn paint_frames(
+    args: &Args,
+    frames: &[AnimFrame],
+    glyph_cache: &mut GlyphCache,
+    palette: &str,
+    script: Option<&Script>,
+) -> (Vec<String>, Vec<Duration>) {
+    let line_height = args.cell_aspect;
+
+    let rendered = frames
+        .iter()
+        .map(|f| {
+            let char_matrix = match args.mode {
+                Mode::Values => {
+                    paint_values(&f.img, args.cols, line_height, args.invert, palette, script)
+                }
+                Mode::PixelMatch => paint_flat(
+                    &f.img,
+                    args.cols,
+                    line_height,
+                    args.invert,
+                    palette,
+                    glyph_cache,
+                    args.threads,
+                ),
+                Mode::Sixel => unreachable!("Mode::Sixel is handled before paint() is called"),
+            };
+            render_char_matrix(&char_matrix, args.cols)
+        })
+        .collect();
+
+    let delays = frames.iter().map(|f| f.delay).collect();
+    (rendered, delays)
 }
 
 fn main() -> io::Result<()> {
     let args = Args::parse();
-    let img = image::open(&Path::new(&args.img)).unwrap();
-
-    // check if /cache dir exists, if not create it
-    if !Path::new("cache").exists() {
-        fs::create_dir("cache").unwrap();
-    }
+    let path = Path::new(&args.img);
+    let img = image::open(path).unwrap();
 
     println!("dimensions {:?}", img.dimensions());
     println!("color {:?}", img.color());
-    println!("palette {:?}: {}", args.palette, PALETTE[args.palette]);
 
-    let result = paint(args, img);
+    // SIXEL output carries its own truecolor payload, so it bypasses the
+    // ratatui Paragraph (and the char palette) entirely: write it straight
+    // to stdout and exit.
+    if let Mode::Sixel = args.mode {
+        let payload = sixel::encode(&img, args.sixel_colors);
+        io::stdout().write_all(&payload)?;
+        return Ok(());
+    }
+
+    let script = args
+        .script
+        .as_ref()
+        .map(|script_path| {
+            let context = script::ImageContext {
+                width: img.width(),
+                height: img.height(),
+            };
+            Script::load(Path::new(script_path), context)
+                .unwrap_or_else(|e| panic!("failed to load script {}: {}", script_path, e))
+        });
+
+    let palette = resolve_palette(&args.palette, script.as_ref())
+        .unwrap_or_else(|e| panic!("{}", e));
+    println!("palette {:?}: {}", args.palette, palette);
+
+    let mut glyph_cache = GlyphCache::new("cache", args.glyph_cache_mb, args.disk_cache_mb);
+
+    let (frames, delays) = if is_gif(path) {
+        match load_gif_frames(path, args.fps) {
+            Ok(anim_frames) if anim_frames.len() > 1 => paint_frames(
+                &args,
+                &anim_frames,
+                &mut glyph_cache,
+                &palette,
+                script.as_ref(),
+            ),
+            _ => (
+                vec![paint(&args, &img, &mut glyph_cache, &palette, script.as_ref())],
+                vec![Duration::ZERO],
+            ),
+        }
+    } else {
+        (
+            vec![paint(&args, &img, &mut glyph_cache, &palette, script.as_ref())],
+            vec![Duration::ZERO],
+        )
+    };
+
+    let memory_report = glyph_cache.report();
+    println!(
+        "glyph cache: {} glyphs, {} B resident, {} B on disk, {} hits / {} misses",
+        memory_report.glyph_count,
+        memory_report.resident_bytes,
+        memory_report.disk_bytes,
+        memory_report.hits,
+        memory_report.misses
+    );
 
     let mut terminal = ratatui::init();
     // terminal.clear()?;
-    let app_result = App {
-        result,
-        exit: false,
-    }
-    .run(&mut terminal);
+    let app_result = App::new(frames, delays, memory_report).run(&mut terminal);
     ratatui::restore();
     app_result
 }
+
+#[cfg(test)]
+mod render_char_matrix_tests {
+    use super::*;
+
+    fn row(glyphs: &[&str]) -> Vec<String> {
+        glyphs.iter().map(|g| g.to_string()).collect()
+    }
+
+    #[test]
+    fn narrow_only_palette_fills_every_column() {
+        let matrix = row(&["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(render_char_matrix(&matrix, 3), "abc\ndef\n");
+    }
+
+    #[test]
+    fn wide_only_row_does_not_overshoot_cols() {
+        // every glyph is double-width ("国"); 11 cols can only fit 5 of
+        // them (10 columns), so the 11th sampled cell must be replaced by a
+        // space rather than pushing the line to 12 display columns
+        let matrix = row(&["国"; 11]);
+        let rendered = render_char_matrix(&matrix, 11);
+        let line = rendered.lines().next().unwrap();
+        assert_eq!(line.width(), 11);
+        assert_eq!(line, "国国国国国 ");
+    }
+}
+
+#[cfg(test)]
+mod paint_flat_tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "paint_flat_test_{}_{}_{}",
+            name,
+            std::process::id(),
+            nonce
+        ))
+    }
+
+    #[test]
+    fn serial_and_parallel_execution_agree() {
+        let img = image::DynamicImage::ImageRgb8(RgbImage::from_fn(20, 20, |x, y| {
+            Rgb([((x + y) * 6) as u8; 3])
+        }));
+        let palette = " .#";
+        let cols = 8;
+        let line_height = 2.0;
+
+        let serial_dir = temp_cache_dir("serial");
+        let mut serial_cache = GlyphCache::new(&serial_dir, 16, 16);
+        let serial = paint_flat(
+            &img,
+            cols,
+            line_height,
+            false,
+            palette,
+            &mut serial_cache,
+            Some(1),
+        );
+
+        let parallel_dir = temp_cache_dir("parallel");
+        let mut parallel_cache = GlyphCache::new(&parallel_dir, 16, 16);
+        let parallel = paint_flat(
+            &img,
+            cols,
+            line_height,
+            false,
+            palette,
+            &mut parallel_cache,
+            None,
+        );
+
+        assert_eq!(serial, parallel);
+
+        fs::remove_dir_all(&serial_dir).ok();
+        fs::remove_dir_all(&parallel_dir).ok();
+    }
+}
+
+#[cfg(test)]
+mod app_tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn app(frame_count: usize) -> App {
+        let frames = (0..frame_count).map(|i| i.to_string()).collect();
+        let delays = vec![Duration::from_millis(10); frame_count];
+        App::new(frames, delays, MemoryReport::default())
+    }
+
+    #[test]
+    fn step_wraps_around_in_both_directions() {
+        let mut app = app(3);
+        assert_eq!(app.current, 0);
+
+        app.step(true);
+        assert_eq!(app.current, 1);
+        app.step(true);
+        app.step(true);
+        assert_eq!(app.current, 0, "stepping forward past the last frame wraps to the first");
+
+        app.step(false);
+        assert_eq!(
+            app.current, 2,
+            "stepping backward past the first frame wraps to the last"
+        );
+    }
+
+    #[test]
+    fn advance_is_a_no_op_for_a_single_still_frame() {
+        let mut app = app(1);
+        app.last_tick = Some(Instant::now() - Duration::from_secs(1));
+        app.advance();
+        assert_eq!(app.current, 0);
+    }
+
+    #[test]
+    fn advance_steps_once_the_delay_has_elapsed() {
+        let mut app = app(3);
+        app.last_tick = Some(Instant::now() - Duration::from_secs(1));
+        app.advance();
+        assert_eq!(app.current, 1);
+    }
+
+    #[test]
+    fn advance_honors_reverse_and_pause() {
+        let mut app = app(3);
+        app.reverse = true;
+        app.last_tick = Some(Instant::now() - Duration::from_secs(1));
+        app.advance();
+        assert_eq!(app.current, 2, "reverse playback steps backward");
+
+        let before = app.current;
+        app.playing = false;
+        app.last_tick = Some(Instant::now() - Duration::from_secs(1));
+        app.advance();
+        assert_eq!(app.current, before, "a paused app does not advance");
+    }
+
+    #[test]
+    fn handle_key_event_toggles_play_reverse_and_quits() {
+        let mut app = app(3);
+        assert!(app.playing);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert!(!app.playing);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert!(app.reverse);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+        assert_eq!(app.current, 1);
+        assert!(!app.playing, "stepping manually pauses playback");
+
+        assert!(!app.exit);
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert!(app.exit);
+    }
+}
+
+#[cfg(test)]
+mod gif_tests {
+    use super::*;
+
+    #[test]
+    fn is_gif_matches_extension_case_insensitively() {
+        assert!(is_gif(Path::new("animation.gif")));
+        assert!(is_gif(Path::new("animation.GIF")));
+        assert!(!is_gif(Path::new("picture.png")));
+        assert!(!is_gif(Path::new("no_extension")));
+    }
+}
@@ -0,0 +1,123 @@
+//! Embeddable scripting hooks for custom palettes and per-pixel transforms.
+//!
+//! A `--script <file>` loads a Rhai script that may define:
+//! - `preprocess(luma, x, y) -> int` applied to a pixel's luma before mapping
+//! - `map(luma, x, y) -> string` (first grapheme cluster used) in place of
+//!   the fixed `PALETTE`-index lookup
+//!
+//! It may also call `register_palette(name, chars)` at the top level to
+//! extend the `--palette` index space with named, script-defined palettes.
+//! Scripts see the source image's dimensions as the `WIDTH`/`HEIGHT` globals.
+
+use rhai::{Engine, Scope, AST};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Read-only image-dimensions context made available to script hooks.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageContext {
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    has_preprocess: bool,
+    has_map: bool,
+    palettes: Rc<RefCell<HashMap<String, String>>>,
+    context: ImageContext,
+}
+
+impl Script {
+    pub fn load(path: &Path, context: ImageContext) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        let palettes: Rc<RefCell<HashMap<String, String>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let registry = palettes.clone();
+        engine.register_fn("register_palette", move |name: &str, chars: &str| {
+            registry.borrow_mut().insert(name.to_string(), chars.to_string());
+        });
+
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let ast = engine.compile(&source).map_err(|e| e.to_string())?;
+
+        // run once so top-level `register_palette(...)` calls take effect;
+        // `map`/`preprocess` are only invoked later, per pixel
+        let mut scope = Scope::new();
+        scope.push_constant("WIDTH", context.width as i64);
+        scope.push_constant("HEIGHT", context.height as i64);
+        let _: rhai::Dynamic = engine
+            .eval_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| e.to_string())?;
+
+        let has_preprocess = ast
+            .iter_functions()
+            .any(|f| f.name == "preprocess" && f.params.len() == 3);
+        let has_map = ast
+            .iter_functions()
+            .any(|f| f.name == "map" && f.params.len() == 3);
+
+        Ok(Script {
+            engine,
+            ast,
+            has_preprocess,
+            has_map,
+            palettes,
+            context,
+        })
+    }
+
+    pub fn has_map(&self) -> bool {
+        self.has_map
+    }
+
+    fn scope(&self) -> Scope<'static> {
+        let mut scope = Scope::new();
+        scope.push_constant("WIDTH", self.context.width as i64);
+        scope.push_constant("HEIGHT", self.context.height as i64);
+        scope
+    }
+
+    /// Applies the script's `preprocess` hook, falling back to `luma`
+    /// unchanged if the script doesn't define one.
+    pub fn preprocess(&self, luma: u8, x: u32, y: u32) -> u8 {
+        if !self.has_preprocess {
+            return luma;
+        }
+        self.engine
+            .call_fn::<i64>(
+                &mut self.scope(),
+                &self.ast,
+                "preprocess",
+                (luma as i64, x as i64, y as i64),
+            )
+            .map(|v| v.clamp(0, 255) as u8)
+            .unwrap_or(luma)
+    }
+
+    /// Applies the script's `map` hook. Only call this when `has_map()`.
+    /// Returns the full grapheme cluster the script produced (not just its
+    /// first `char`), so multi-codepoint glyphs like flag emoji survive
+    /// intact instead of being truncated to a dangling scalar.
+    pub fn map(&self, luma: u8, x: u32, y: u32) -> String {
+        self.engine
+            .call_fn::<String>(
+                &mut self.scope(),
+                &self.ast,
+                "map",
+                (luma as i64, x as i64, y as i64),
+            )
+            .ok()
+            .and_then(|s| s.graphemes(true).next().map(|g| g.to_string()))
+            .unwrap_or_else(|| " ".to_string())
+    }
+
+    /// Looks up a palette registered by the script via `register_palette`.
+    pub fn palette(&self, name: &str) -> Option<String> {
+        self.palettes.borrow().get(name).cloned()
+    }
+}
@@ -0,0 +1,122 @@
+//! SIXEL encoder for truecolor terminal output.
+//!
+//! Encodes an image into a SIXEL (DECSIXEL) escape sequence so terminals
+//! that support it (xterm, foot, WezTerm) render real pixels instead of
+//! ASCII glyphs. The image is quantized to a small palette, split into
+//! 6-pixel-tall bands, and each band/color pair is emitted as a run of
+//! sixel bytes (`0x3F + bitmask`, low bit = top pixel of the band).
+
+use image::DynamicImage;
+use std::collections::BTreeSet;
+
+const SIXEL_BAND_HEIGHT: u32 = 6;
+
+/// Quantizes `img` to at most `max_colors` colors and returns the full
+/// SIXEL payload (DCS introducer through ST terminator), ready to be
+/// written straight to stdout.
+pub fn encode(img: &DynamicImage, max_colors: usize) -> Vec<u8> {
+    let img = img.to_rgb8();
+    let (width, height) = img.dimensions();
+    let palette = quantize(&img, max_colors);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"\x1bPq");
+    for (i, color) in palette.iter().enumerate() {
+        out.extend_from_slice(
+            format!(
+                "#{};2;{};{};{}",
+                i,
+                color[0] as u32 * 100 / 255,
+                color[1] as u32 * 100 / 255,
+                color[2] as u32 * 100 / 255,
+            )
+            .as_bytes(),
+        );
+    }
+
+    let bands = height.div_ceil(SIXEL_BAND_HEIGHT);
+    for band in 0..bands {
+        let y0 = band * SIXEL_BAND_HEIGHT;
+        let band_h = SIXEL_BAND_HEIGHT.min(height - y0);
+
+        for (ci, _) in palette.iter().enumerate() {
+            out.extend_from_slice(format!("#{}", ci).as_bytes());
+            for x in 0..width {
+                let mut bits = 0u8;
+                for dy in 0..band_h {
+                    let px = img.get_pixel(x, y0 + dy).0;
+                    if nearest(&palette, px) == ci {
+                        bits |= 1 << dy;
+                    }
+                }
+                out.push(0x3F + bits);
+            }
+            out.push(b'$');
+        }
+        out.push(b'-');
+    }
+
+    out.extend_from_slice(b"\x1b\\");
+    out
+}
+
+/// Uniform quantization: each channel is snapped to one of a handful of
+/// levels. Cheap compared to median-cut and good enough for a terminal
+/// preview where the palette only needs to stay under a few hundred entries.
+fn quantize(img: &image::RgbImage, max_colors: usize) -> Vec<[u8; 3]> {
+    let levels = (max_colors as f64).cbrt().floor().max(2.0) as u32;
+    let step = (256 / levels).max(1);
+
+    let mut seen = BTreeSet::new();
+    for p in img.pixels() {
+        seen.insert([
+            (p.0[0] as u32 / step * step) as u8,
+            (p.0[1] as u32 / step * step) as u8,
+            (p.0[2] as u32 / step * step) as u8,
+        ]);
+    }
+    seen.into_iter().take(max_colors).collect()
+}
+
+fn nearest(palette: &[[u8; 3]], px: [u8; 3]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| {
+            let dr = c[0] as i32 - px[0] as i32;
+            let dg = c[1] as i32 - px[1] as i32;
+            let db = c[2] as i32 - px[2] as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn encode_frames_payload_with_dcs_and_st_and_one_band_per_6px() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(4, 13, |x, y| {
+            Rgb([(x * 60) as u8, (y * 20) as u8, 128])
+        }));
+        let payload = encode(&img, 8);
+
+        assert!(payload.starts_with(b"\x1bPq"));
+        assert!(payload.ends_with(b"\x1b\\"));
+
+        // 13px tall at 6px bands -> 3 bands, each closed with a '-'
+        let band_count = payload.iter().filter(|&&b| b == b'-').count();
+        assert_eq!(band_count, 3);
+    }
+
+    #[test]
+    fn quantize_never_exceeds_max_colors() {
+        let img = RgbImage::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 0]));
+        let palette = quantize(&img, 4);
+        assert!(!palette.is_empty());
+        assert!(palette.len() <= 4);
+    }
+}